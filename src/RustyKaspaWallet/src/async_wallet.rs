@@ -0,0 +1,483 @@
+//! Async-native wallet core.
+//!
+//! Every RPC-touching operation here is a plain `async fn` (or returns a [`Stream`]) built to
+//! run on whatever executor the caller already has, with no internal runtime or `block_on`.
+//! [`crate::RustyKaspaWallet`] is a thin blocking wrapper that owns a private
+//! [`tokio::runtime::Runtime`] and drives this type with `block_on`; reach for
+//! [`AsyncRustyKaspaWallet`] directly to embed the wallet in an existing async service.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use futures::stream::{Stream, StreamExt};
+use kaspa_addresses::{Address, Prefix, Version};
+use kaspa_bip32::{
+    DerivationPath, ExtendedPrivateKey, Language, Mnemonic, Prefix as KeyPrefix, SecretKey,
+    SecretKeyExt,
+};
+use kaspa_consensus_core::sign::{sign_with_multiple_v2, Signed};
+use kaspa_consensus_core::subnets::SUBNETWORK_ID_NATIVE;
+use kaspa_consensus_core::tx::{
+    SignableTransaction, Transaction, TransactionInput, TransactionOutput, UtxoEntry,
+};
+use kaspa_grpc_client::GrpcClient;
+use kaspa_notify::connection::ChannelType;
+use kaspa_notify::listener::ListenerId;
+use kaspa_notify::scope::{Scope, UtxosChangedScope, VirtualChainChangedScope};
+use kaspa_rpc_core::api::rpc::RpcApi;
+use kaspa_rpc_core::model::address::RpcUtxosByAddressesEntry;
+use kaspa_rpc_core::model::message::GetServerInfoResponse;
+use kaspa_rpc_core::model::tx::{RpcTransaction, RpcTransactionId, RpcTransactionOutpoint};
+use kaspa_rpc_core::notify::connection::ChannelConnection;
+use kaspa_rpc_core::Notification;
+use kaspa_txscript::pay_to_address_script;
+use kaspa_wrpc_client::client::{ConnectOptions, ConnectStrategy};
+use kaspa_wrpc_client::prelude::{NetworkId, NetworkType, WrpcEncoding};
+use kaspa_wrpc_client::KaspaRpcClient;
+
+use crate::{
+    select_coins, AccountScanResult, AddressBalance, DerivedKey, UtxoChanged, VirtualChainChanged,
+    WalletConfig, WalletError, DEFAULT_GAP_LIMIT,
+};
+
+/// Async-native counterpart of [`crate::RustyKaspaWallet`]. Every method is an `async fn`
+/// returning a future (or a [`Stream`] for the notification subscriptions) that runs on
+/// whatever executor polls it, instead of blocking on a runtime the wallet owns itself.
+pub struct AsyncRustyKaspaWallet {
+    network_type: NetworkType,
+    wrpc_client: Option<Arc<KaspaRpcClient>>,
+    grpc_client: Option<Arc<GrpcClient>>,
+    subscriptions: Mutex<Vec<ListenerId>>,
+}
+
+impl AsyncRustyKaspaWallet {
+    pub async fn connect(config: WalletConfig) -> Result<Self, WalletError> {
+        let mut wrpc_client = None;
+        let mut grpc_client = None;
+        let network_id = NetworkId::new(config.network_type);
+
+        if let Some(url) = &config.wrpc_url {
+            let client = KaspaRpcClient::new(
+                config.wrpc_encoding,
+                Some(url.as_str()),
+                None,
+                Some(network_id),
+                None,
+            )?;
+            let connect_opts = ConnectOptions {
+                block_async_connect: true,
+                connect_timeout: config.wrpc_connect_timeout,
+                strategy: ConnectStrategy::Fallback,
+                ..Default::default()
+            };
+            client.connect(Some(connect_opts)).await?;
+            client.start().await?;
+            ensure_utxo_index(&client).await?;
+            wrpc_client = Some(Arc::new(client));
+        }
+
+        if let Some(url) = &config.grpc_url {
+            let grpc_url = if url.starts_with("grpc://") {
+                url.clone()
+            } else {
+                format!("grpc://{url}")
+            };
+            let client = GrpcClient::connect(grpc_url).await?;
+            client.start(None).await;
+            ensure_utxo_index_grpc(&client).await?;
+            grpc_client = Some(Arc::new(client));
+        }
+
+        if wrpc_client.is_none() && grpc_client.is_none() {
+            return Err(WalletError::NoEndpoints);
+        }
+
+        Ok(Self {
+            network_type: config.network_type,
+            wrpc_client,
+            grpc_client,
+            subscriptions: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub async fn get_utxos(&self, address: &str) -> Result<Vec<RpcUtxosByAddressesEntry>, WalletError> {
+        let rpc_address: Address = address.try_into()?;
+
+        if let Some(client) = &self.wrpc_client {
+            return client
+                .get_utxos_by_addresses(vec![rpc_address])
+                .await
+                .map_err(WalletError::from);
+        }
+
+        if let Some(client) = &self.grpc_client {
+            return client
+                .get_utxos_by_addresses(vec![rpc_address])
+                .await
+                .map_err(WalletError::from);
+        }
+
+        Err(WalletError::NoEndpoints)
+    }
+
+    pub async fn broadcast_transaction(
+        &self,
+        transaction: &Transaction,
+        allow_orphans: bool,
+    ) -> Result<RpcTransactionId, WalletError> {
+        let rpc_tx = RpcTransaction::from(transaction);
+
+        if let Some(client) = &self.wrpc_client {
+            return client
+                .submit_transaction(rpc_tx, allow_orphans)
+                .await
+                .map_err(WalletError::from);
+        }
+
+        if let Some(client) = &self.grpc_client {
+            return client
+                .submit_transaction(rpc_tx, allow_orphans)
+                .await
+                .map_err(WalletError::from);
+        }
+
+        Err(WalletError::NoEndpoints)
+    }
+
+    /// Signing is pure CPU-bound work; this stays `async` so the API surface is uniform, with
+    /// nothing to actually await.
+    #[allow(clippy::unused_async)]
+    pub async fn sign_transaction(
+        &self,
+        transaction: Transaction,
+        utxos: &[RpcUtxosByAddressesEntry],
+        private_keys: &[String],
+    ) -> Result<Transaction, WalletError> {
+        let mut utxo_map: HashMap<RpcTransactionOutpoint, UtxoEntry> = HashMap::new();
+        for entry in utxos.iter() {
+            let utxo = entry.utxo_entry.clone().into();
+            utxo_map.insert(entry.outpoint, utxo);
+        }
+
+        let mut entries = Vec::with_capacity(transaction.inputs.len());
+        for input in &transaction.inputs {
+            let rpc_outpoint: RpcTransactionOutpoint = input.previous_outpoint.into();
+            let utxo = utxo_map
+                .get(&rpc_outpoint)
+                .cloned()
+                .ok_or_else(|| WalletError::MissingUtxo(input.previous_outpoint))?;
+            entries.push(utxo);
+        }
+
+        let signable = SignableTransaction::with_entries(transaction, entries);
+        let mut key_bytes = Vec::with_capacity(private_keys.len());
+        for key in private_keys {
+            let data = hex::decode(key)?;
+            let array: [u8; 32] = data
+                .as_slice()
+                .try_into()
+                .map_err(|_| WalletError::Hex(hex::FromHexError::InvalidStringLength))?;
+            key_bytes.push(array);
+        }
+
+        let signed = sign_with_multiple_v2(signable, &key_bytes);
+        let completed = match signed {
+            Signed::Fully(tx) => tx,
+            Signed::Partially(_) => return Err(WalletError::PartialSignature),
+        };
+        let mut tx = completed.tx;
+        tx.finalize();
+        Ok(tx)
+    }
+
+    /// Builds an unsigned transaction paying `outputs` from UTXOs owned by `from_addresses`,
+    /// carrying `payload` as its attached payload bytes. See
+    /// [`crate::RustyKaspaWallet::build_transaction`] for the selection/fee details.
+    pub async fn build_transaction(
+        &self,
+        from_addresses: &[String],
+        outputs: Vec<(Address, u64)>,
+        fee_rate: u64,
+        payload: &[u8],
+    ) -> Result<(Transaction, Vec<RpcUtxosByAddressesEntry>), WalletError> {
+        let change_address: Address = from_addresses
+            .first()
+            .ok_or(WalletError::NoSourceAddresses)?
+            .as_str()
+            .try_into()?;
+
+        let mut spendable = Vec::new();
+        for address in from_addresses {
+            spendable.extend(self.get_utxos(address).await?);
+        }
+
+        let target: u64 = outputs.iter().map(|(_, amount)| amount).sum();
+        let selection = select_coins(&spendable, outputs.len(), payload.len(), target, fee_rate)?;
+
+        let mut tx_outputs: Vec<TransactionOutput> = outputs
+            .iter()
+            .map(|(address, amount)| TransactionOutput::new(*amount, pay_to_address_script(address)))
+            .collect();
+        if selection.change > 0 {
+            tx_outputs.push(TransactionOutput::new(
+                selection.change,
+                pay_to_address_script(&change_address),
+            ));
+        }
+
+        // Each input spends a standard single-sig (schnorr) p2pk output, so it declares exactly
+        // one signature operation; consensus rejects a transaction whose declared count doesn't
+        // match what the signed script actually does.
+        let tx_inputs: Vec<TransactionInput> = selection
+            .utxos
+            .iter()
+            .map(|entry| TransactionInput::new(entry.outpoint.into(), vec![], 0, 1))
+            .collect();
+
+        let transaction = Transaction::new(
+            0,
+            tx_inputs,
+            tx_outputs,
+            0,
+            SUBNETWORK_ID_NATIVE,
+            0,
+            payload.to_vec(),
+        );
+
+        Ok((transaction, selection.utxos))
+    }
+
+    /// Key derivation is pure CPU-bound work; see [`Self::sign_transaction`] for why it's still
+    /// `async`.
+    #[allow(clippy::unused_async)]
+    pub async fn derive_private_key(&self, mnemonic: &str, path: &str) -> Result<DerivedKey, WalletError> {
+        let mnemonic = Mnemonic::new(mnemonic, Language::English)?;
+        let seed = mnemonic.to_seed("");
+        let master = ExtendedPrivateKey::<SecretKey>::new(seed.as_bytes())?;
+        derive_from_master(self.network_type, &master, path)
+    }
+
+    /// Scans the receive and change chains of `account_index` using the default gap limit of
+    /// [`DEFAULT_GAP_LIMIT`]. See [`Self::scan_accounts_with_gap_limit`] for details.
+    pub async fn scan_accounts(&self, mnemonic: &str, account_index: u32) -> Result<AccountScanResult, WalletError> {
+        self.scan_accounts_with_gap_limit(mnemonic, account_index, DEFAULT_GAP_LIMIT).await
+    }
+
+    /// Walks `m/44'/111111'/account_index'/0/i` (receive) and `.../1/i` (change), deriving each
+    /// address in turn and checking it for UTXOs via [`Self::get_utxos`]. A chain stops once
+    /// `gap_limit` consecutive addresses come back empty. `gap_limit` must be at least 1 — at
+    /// zero, index 0 of each chain would never even be checked, silently returning an empty scan.
+    pub async fn scan_accounts_with_gap_limit(
+        &self,
+        mnemonic: &str,
+        account_index: u32,
+        gap_limit: u32,
+    ) -> Result<AccountScanResult, WalletError> {
+        if gap_limit == 0 {
+            return Err(WalletError::InvalidGapLimit);
+        }
+
+        let mnemonic = Mnemonic::new(mnemonic, Language::English)?;
+        let seed = mnemonic.to_seed("");
+        let master = ExtendedPrivateKey::<SecretKey>::new(seed.as_bytes())?;
+
+        let receive = self.scan_chain(&master, account_index, 0, gap_limit).await?;
+        let change = self.scan_chain(&master, account_index, 1, gap_limit).await?;
+        let total_balance = receive.iter().chain(change.iter()).map(|a| a.balance).sum();
+
+        Ok(AccountScanResult {
+            receive,
+            change,
+            total_balance,
+        })
+    }
+
+    async fn scan_chain(
+        &self,
+        master: &ExtendedPrivateKey<SecretKey>,
+        account_index: u32,
+        chain: u32,
+        gap_limit: u32,
+    ) -> Result<Vec<AddressBalance>, WalletError> {
+        let mut found = Vec::new();
+        let mut index = 0u32;
+        let mut consecutive_empty = 0u32;
+
+        while consecutive_empty < gap_limit {
+            let path = format!("m/44'/111111'/{account_index}'/{chain}/{index}");
+            let derived_key = derive_from_master(self.network_type, master, &path)?;
+            let utxos = self.get_utxos(&derived_key.address).await?;
+
+            if utxos.is_empty() {
+                consecutive_empty += 1;
+            } else {
+                consecutive_empty = 0;
+                let balance = utxos.iter().map(|entry| entry.utxo_entry.amount).sum();
+                found.push(AddressBalance {
+                    derived_key,
+                    balance,
+                    utxos,
+                });
+            }
+
+            index += 1;
+        }
+
+        Ok(found)
+    }
+
+    /// Registers for UTXO-changed notifications on `addresses` and returns the listener along
+    /// with a stream of [`UtxoChanged`] events that lives as long as the subscription stays
+    /// registered. Requires a wRPC connection; gRPC does not expose notification channels in
+    /// this crate.
+    pub async fn watch_addresses(
+        &self,
+        addresses: Vec<Address>,
+    ) -> Result<(ListenerId, impl Stream<Item = UtxoChanged> + Unpin + 'static), WalletError> {
+        let client = self.wrpc_client.as_ref().ok_or(WalletError::NoEndpoints)?.clone();
+
+        let (notification_sender, notification_receiver) = async_channel::unbounded();
+        let connection = ChannelConnection::new("utxo-changed", notification_sender, ChannelType::Closable);
+        let listener_id = client.register_new_listener(connection);
+        client
+            .start_notify(listener_id, Scope::UtxosChanged(UtxosChangedScope::new(addresses)))
+            .await?;
+        self.subscriptions.lock().unwrap().push(listener_id);
+
+        let stream = notification_receiver.filter_map(|notification| async move {
+            match notification {
+                Notification::UtxosChanged(event) => Some(UtxoChanged {
+                    added: event.added.as_ref().clone(),
+                    removed: event.removed.as_ref().clone(),
+                }),
+                _ => None,
+            }
+        });
+
+        Ok((listener_id, stream))
+    }
+
+    /// Registers for virtual-chain-changed notifications and returns the listener along with a
+    /// stream of [`VirtualChainChanged`] events as the DAG's selected chain advances.
+    pub async fn subscribe_virtual_chain(
+        &self,
+    ) -> Result<(ListenerId, impl Stream<Item = VirtualChainChanged> + Unpin + 'static), WalletError> {
+        let client = self.wrpc_client.as_ref().ok_or(WalletError::NoEndpoints)?.clone();
+
+        let (notification_sender, notification_receiver) = async_channel::unbounded();
+        let connection = ChannelConnection::new("virtual-chain-changed", notification_sender, ChannelType::Closable);
+        let listener_id = client.register_new_listener(connection);
+        client
+            .start_notify(listener_id, Scope::VirtualChainChanged(VirtualChainChangedScope::new(true)))
+            .await?;
+        self.subscriptions.lock().unwrap().push(listener_id);
+
+        let stream = notification_receiver.filter_map(|notification| async move {
+            match notification {
+                Notification::VirtualChainChanged(event) => {
+                    let accepted_transaction_ids = event
+                        .accepted_transaction_ids
+                        .iter()
+                        .flat_map(|accepted| accepted.accepting_block_transaction_ids.iter().copied())
+                        .collect();
+                    Some(VirtualChainChanged {
+                        accepted_transaction_ids,
+                        removed_chain_block_hashes: event.removed_chain_block_hashes.as_ref().clone(),
+                    })
+                }
+                _ => None,
+            }
+        });
+
+        Ok((listener_id, stream))
+    }
+
+    /// Unregisters a listener previously returned by [`Self::watch_addresses`] or
+    /// [`Self::subscribe_virtual_chain`] (see their respective subscription handles).
+    pub(crate) async fn unregister_listener(&self, listener_id: ListenerId) -> Result<(), WalletError> {
+        let client = self.wrpc_client.as_ref().ok_or(WalletError::NoEndpoints)?;
+        client.unregister_listener(listener_id).await?;
+        self.subscriptions.lock().unwrap().retain(|id| *id != listener_id);
+        Ok(())
+    }
+
+    /// Clones the underlying wRPC client handle, for subscription handles that need to
+    /// unregister themselves from a synchronous `Drop` impl without going through `self`.
+    /// `None` when only gRPC is configured.
+    pub(crate) fn wrpc_client(&self) -> Option<Arc<KaspaRpcClient>> {
+        self.wrpc_client.clone()
+    }
+
+    /// Unregisters every live subscription and tears down the underlying RPC connections.
+    /// Called by [`crate::RustyKaspaWallet`]'s `Drop` impl; async consumers that manage their own
+    /// shutdown sequencing can call it directly instead.
+    pub async fn shutdown(&mut self) {
+        if let Some(client) = self.wrpc_client.take() {
+            let listener_ids = std::mem::take(&mut *self.subscriptions.lock().unwrap());
+            for listener_id in listener_ids {
+                client.unregister_listener(listener_id).await.ok();
+            }
+            client.stop().await.ok();
+            client.disconnect().await.ok();
+        }
+        if let Some(client) = self.grpc_client.take() {
+            client.join().await.ok();
+            client.disconnect().await.ok();
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn offline(network_type: NetworkType) -> Self {
+        Self {
+            network_type,
+            wrpc_client: None,
+            grpc_client: None,
+            subscriptions: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+fn derive_from_master(
+    network_type: NetworkType,
+    master: &ExtendedPrivateKey<SecretKey>,
+    path: &str,
+) -> Result<DerivedKey, WalletError> {
+    let derivation_path = DerivationPath::from_str(path)?;
+    let child = master.derive_path(&derivation_path)?;
+    let secret_key = child.private_key();
+    let public_key = secret_key.get_public_key();
+    let (x_only, _) = public_key.x_only_public_key();
+    let prefix = Prefix::from(network_type);
+    let address = Address::new(prefix, Version::PubKey, &x_only.serialize());
+
+    let extended = child.to_string(KeyPrefix::KPRV);
+    let private_key_hex = hex::encode(secret_key.secret_bytes());
+    let public_key_hex = hex::encode(public_key.serialize());
+    let x_only_public_key_hex = hex::encode(x_only.serialize());
+
+    Ok(DerivedKey {
+        extended_private_key: extended.to_string(),
+        private_key_hex,
+        public_key_hex,
+        x_only_public_key_hex,
+        address: address.to_string(),
+    })
+}
+
+async fn ensure_utxo_index(client: &KaspaRpcClient) -> Result<(), WalletError> {
+    let response = client.get_server_info().await?;
+    enforce_utxo_index(response)
+}
+
+async fn ensure_utxo_index_grpc(client: &GrpcClient) -> Result<(), WalletError> {
+    let response = client.get_server_info().await?;
+    enforce_utxo_index(response)
+}
+
+fn enforce_utxo_index(info: GetServerInfoResponse) -> Result<(), WalletError> {
+    if !info.has_utxo_index {
+        return Err(WalletError::MissingUtxoIndex);
+    }
+    Ok(())
+}
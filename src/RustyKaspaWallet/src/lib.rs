@@ -1,27 +1,34 @@
-use std::collections::HashMap;
-use std::str::FromStr;
-use std::sync::Arc;
 use std::time::Duration;
 
-use kaspa_addresses::{Address, AddressError, Prefix, Version};
-use kaspa_bip32::{
-    DerivationPath, ExtendedPrivateKey, Language, Mnemonic, Prefix as KeyPrefix, SecretKey,
-    SecretKeyExt,
-};
-use kaspa_consensus_core::sign::{sign_with_multiple_v2, Signed};
-use kaspa_consensus_core::tx::{SignableTransaction, Transaction, TransactionOutpoint, UtxoEntry};
-use kaspa_grpc_client::GrpcClient;
-use kaspa_rpc_core::api::rpc::RpcApi;
+use kaspa_addresses::{Address, AddressError};
+use kaspa_consensus_core::tx::{Transaction, TransactionOutpoint};
 use kaspa_rpc_core::error::RpcError;
 use kaspa_rpc_core::model::address::RpcUtxosByAddressesEntry;
-use kaspa_rpc_core::model::message::GetServerInfoResponse;
-use kaspa_rpc_core::model::tx::{RpcTransaction, RpcTransactionId, RpcTransactionOutpoint};
-use kaspa_wrpc_client::client::{ConnectOptions, ConnectStrategy};
-use kaspa_wrpc_client::prelude::{NetworkId, NetworkType, WrpcEncoding};
-use kaspa_wrpc_client::KaspaRpcClient;
+use kaspa_rpc_core::model::tx::RpcTransactionId;
+use kaspa_wrpc_client::prelude::{NetworkType, WrpcEncoding};
 use thiserror::Error;
 use tokio::runtime::Runtime;
 
+mod async_wallet;
+mod notify;
+mod payload;
+mod storage;
+pub use async_wallet::AsyncRustyKaspaWallet;
+pub use notify::{UtxoChanged, UtxoSubscription, VirtualChainChanged, VirtualChainSubscription};
+pub use payload::{decrypt_memo, read_payload};
+pub use storage::WalletSecret;
+
+/// Dust threshold below which a change output is dropped instead of created, in sompi.
+const DUST_THRESHOLD: u64 = 546;
+/// Rough serialized size contribution of a single signed input, in bytes.
+const INPUT_MASS: u64 = 148;
+/// Rough serialized size contribution of a single output, in bytes.
+const OUTPUT_MASS: u64 = 43;
+/// Rough fixed overhead of a transaction (version, lock time, etc), in bytes.
+const BASE_MASS: u64 = 10;
+/// Upper bound on branch-and-bound search iterations before giving up.
+const BNB_MAX_TRIES: usize = 100_000;
+
 #[derive(Clone, Debug)]
 pub struct WalletConfig {
     pub network_type: NetworkType,
@@ -75,6 +82,16 @@ pub enum WalletError {
     PartialSignature,
     #[error("kaspa node does not expose the UTXO index; restart kaspad with --utxoindex")]
     MissingUtxoIndex,
+    #[error("no source addresses supplied to build a transaction from")]
+    NoSourceAddresses,
+    #[error("gap limit must be at least 1, or address index 0 is never scanned")]
+    InvalidGapLimit,
+    #[error("insufficient funds: available balance does not cover outputs plus fee")]
+    InsufficientFunds,
+    #[error("failed to decrypt wallet file: wrong passphrase or corrupted file")]
+    Decrypt,
+    #[error("wallet file I/O error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 #[derive(Clone, Debug)]
@@ -86,82 +103,78 @@ pub struct DerivedKey {
     pub address: String,
 }
 
-pub struct RustyKaspaWallet {
-    runtime: Runtime,
-    network_type: NetworkType,
-    wrpc_client: Option<Arc<KaspaRpcClient>>,
-    grpc_client: Option<Arc<GrpcClient>>,
+/// Number of consecutive unused addresses scanned before a chain is considered exhausted.
+const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// A derived address that held funds during a [`RustyKaspaWallet::scan_accounts`] pass.
+#[derive(Clone, Debug)]
+pub struct AddressBalance {
+    pub derived_key: DerivedKey,
+    pub balance: u64,
+    pub utxos: Vec<RpcUtxosByAddressesEntry>,
 }
 
-impl RustyKaspaWallet {
-    pub fn connect(config: WalletConfig) -> Result<Self, WalletError> {
-        let runtime = Runtime::new().expect("failed to start tokio runtime");
-        let mut wrpc_client = None;
-        let mut grpc_client = None;
-        let network_id = NetworkId::new(config.network_type);
-
-        if let Some(url) = &config.wrpc_url {
-            let client = KaspaRpcClient::new(
-                config.wrpc_encoding,
-                Some(url.as_str()),
-                None,
-                Some(network_id),
-                None,
-            )?;
-            let connect_opts = ConnectOptions {
-                block_async_connect: true,
-                connect_timeout: config.wrpc_connect_timeout,
-                strategy: ConnectStrategy::Fallback,
-                ..Default::default()
-            };
-            runtime.block_on(client.connect(Some(connect_opts)))?;
-            runtime.block_on(client.start())?;
-            ensure_utxo_index(&runtime, &client)?;
-            wrpc_client = Some(Arc::new(client));
-        }
+/// The result of scanning an HD account's receive and change chains for activity.
+#[derive(Clone, Debug)]
+pub struct AccountScanResult {
+    pub receive: Vec<AddressBalance>,
+    pub change: Vec<AddressBalance>,
+    pub total_balance: u64,
+}
 
-        if let Some(url) = &config.grpc_url {
-            let grpc_url = if url.starts_with("grpc://") {
-                url.clone()
-            } else {
-                format!("grpc://{url}")
-            };
-            let client = runtime.block_on(GrpcClient::connect(grpc_url.clone()))?;
-            runtime.block_on(client.start(None));
-            ensure_utxo_index_grpc(&runtime, &client)?;
-            grpc_client = Some(Arc::new(client));
-        }
+/// Builds a transaction via [`RustyKaspaWallet::transaction`], optionally attaching a payload
+/// (application-defined bytes, or an encrypted memo via [`Self::with_encrypted_memo`]) before
+/// input selection is finalized.
+pub struct TransactionBuilder<'a> {
+    wallet: &'a RustyKaspaWallet,
+    from_addresses: Vec<String>,
+    outputs: Vec<(Address, u64)>,
+    fee_rate: u64,
+    payload: Vec<u8>,
+}
 
-        if wrpc_client.is_none() && grpc_client.is_none() {
-            return Err(WalletError::NoEndpoints);
-        }
+impl<'a> TransactionBuilder<'a> {
+    /// Attaches an application-defined payload, overwriting any payload set previously.
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
 
-        Ok(Self {
-            runtime,
-            network_type: config.network_type,
-            wrpc_client,
-            grpc_client,
-        })
+    /// Attaches `memo` encrypted for `recipient_x_only_pubkey_hex`, using a shared secret
+    /// derived via ECDH from `sender_private_key_hex`. See [`payload::encrypt_memo`] for the
+    /// wire format third parties see only as opaque bytes.
+    pub fn with_encrypted_memo(
+        mut self,
+        memo: &[u8],
+        sender_private_key_hex: &str,
+        recipient_x_only_pubkey_hex: &str,
+    ) -> Result<Self, WalletError> {
+        self.payload = payload::encrypt_memo(memo, sender_private_key_hex, recipient_x_only_pubkey_hex)?;
+        Ok(self)
     }
 
-    pub fn get_utxos(&self, address: &str) -> Result<Vec<RpcUtxosByAddressesEntry>, WalletError> {
-        let rpc_address: Address = address.try_into()?;
+    /// Selects inputs and finalizes the transaction, attaching whichever payload was set. The
+    /// payload is priced into the fee before input selection; see [`RustyKaspaWallet::build_transaction`].
+    pub fn build(self) -> Result<(Transaction, Vec<RpcUtxosByAddressesEntry>), WalletError> {
+        self.wallet
+            .build_transaction(&self.from_addresses, self.outputs, self.fee_rate, &self.payload)
+    }
+}
 
-        if let Some(client) = &self.wrpc_client {
-            return self
-                .runtime
-                .block_on(client.get_utxos_by_addresses(vec![rpc_address.clone()]))
-                .map_err(WalletError::from);
-        }
+pub struct RustyKaspaWallet {
+    runtime: Runtime,
+    inner: AsyncRustyKaspaWallet,
+}
 
-        if let Some(client) = &self.grpc_client {
-            return self
-                .runtime
-                .block_on(client.get_utxos_by_addresses(vec![rpc_address]))
-                .map_err(WalletError::from);
-        }
+impl RustyKaspaWallet {
+    pub fn connect(config: WalletConfig) -> Result<Self, WalletError> {
+        let runtime = Runtime::new().expect("failed to start tokio runtime");
+        let inner = runtime.block_on(AsyncRustyKaspaWallet::connect(config))?;
+        Ok(Self { runtime, inner })
+    }
 
-        Err(WalletError::NoEndpoints)
+    pub fn get_utxos(&self, address: &str) -> Result<Vec<RpcUtxosByAddressesEntry>, WalletError> {
+        self.runtime.block_on(self.inner.get_utxos(address))
     }
 
     pub fn broadcast_transaction(
@@ -169,23 +182,8 @@ impl RustyKaspaWallet {
         transaction: &Transaction,
         allow_orphans: bool,
     ) -> Result<RpcTransactionId, WalletError> {
-        let rpc_tx = RpcTransaction::from(transaction);
-
-        if let Some(client) = &self.wrpc_client {
-            return self
-                .runtime
-                .block_on(client.submit_transaction(rpc_tx.clone(), allow_orphans))
-                .map_err(WalletError::from);
-        }
-
-        if let Some(client) = &self.grpc_client {
-            return self
-                .runtime
-                .block_on(client.submit_transaction(rpc_tx, allow_orphans))
-                .map_err(WalletError::from);
-        }
-
-        Err(WalletError::NoEndpoints)
+        self.runtime
+            .block_on(self.inner.broadcast_transaction(transaction, allow_orphans))
     }
 
     pub fn sign_transaction(
@@ -194,41 +192,45 @@ impl RustyKaspaWallet {
         utxos: &[RpcUtxosByAddressesEntry],
         private_keys: &[String],
     ) -> Result<Transaction, WalletError> {
-        let mut utxo_map: HashMap<RpcTransactionOutpoint, UtxoEntry> = HashMap::new();
-        for entry in utxos.iter() {
-            let utxo = entry.utxo_entry.clone().into();
-            utxo_map.insert(entry.outpoint, utxo);
-        }
+        self.runtime
+            .block_on(self.inner.sign_transaction(transaction, utxos, private_keys))
+    }
 
-        let mut entries = Vec::with_capacity(transaction.inputs.len());
-        for input in &transaction.inputs {
-            let rpc_outpoint: RpcTransactionOutpoint = input.previous_outpoint.into();
-            let utxo = utxo_map
-                .get(&rpc_outpoint)
-                .cloned()
-                .ok_or_else(|| WalletError::MissingUtxo(input.previous_outpoint))?;
-            entries.push(utxo);
-        }
+    /// Builds an unsigned transaction paying `outputs` from UTXOs owned by `from_addresses`,
+    /// carrying `payload` as its attached payload bytes (pass `&[]` for none).
+    ///
+    /// Candidate UTXOs are fetched via [`Self::get_utxos`], a fee is estimated from `fee_rate`
+    /// (sompi per byte) and the selected input/output counts plus `payload.len()`, and a change
+    /// output is appended back to the first of `from_addresses` when the selection leaves a
+    /// non-dust surplus. The returned `RpcUtxosByAddressesEntry` list matches the transaction's
+    /// inputs in order so it can be passed straight to [`Self::sign_transaction`].
+    pub fn build_transaction(
+        &self,
+        from_addresses: &[String],
+        outputs: Vec<(Address, u64)>,
+        fee_rate: u64,
+        payload: &[u8],
+    ) -> Result<(Transaction, Vec<RpcUtxosByAddressesEntry>), WalletError> {
+        self.runtime
+            .block_on(self.inner.build_transaction(from_addresses, outputs, fee_rate, payload))
+    }
 
-        let signable = SignableTransaction::with_entries(transaction, entries);
-        let mut key_bytes = Vec::with_capacity(private_keys.len());
-        for key in private_keys {
-            let data = hex::decode(key)?;
-            let array: [u8; 32] = data
-                .as_slice()
-                .try_into()
-                .map_err(|_| WalletError::Hex(hex::FromHexError::InvalidStringLength))?;
-            key_bytes.push(array);
+    /// Starts building a transaction, allowing a payload to be attached before it is finalized
+    /// via [`TransactionBuilder::build`]. Equivalent to [`Self::build_transaction`] when no
+    /// payload is attached.
+    pub fn transaction(
+        &self,
+        from_addresses: Vec<String>,
+        outputs: Vec<(Address, u64)>,
+        fee_rate: u64,
+    ) -> TransactionBuilder<'_> {
+        TransactionBuilder {
+            wallet: self,
+            from_addresses,
+            outputs,
+            fee_rate,
+            payload: Vec::new(),
         }
-
-        let signed = sign_with_multiple_v2(signable, &key_bytes);
-        let completed = match signed {
-            Signed::Fully(tx) => tx,
-            Signed::Partially(_) => return Err(WalletError::PartialSignature),
-        };
-        let mut tx = completed.tx;
-        tx.finalize();
-        Ok(tx)
     }
 
     pub fn derive_private_key(
@@ -236,68 +238,222 @@ impl RustyKaspaWallet {
         mnemonic: &str,
         path: &str,
     ) -> Result<DerivedKey, WalletError> {
-        let mnemonic = Mnemonic::new(mnemonic, Language::English)?;
-        let seed = mnemonic.to_seed("");
-        let master = ExtendedPrivateKey::<SecretKey>::new(seed.as_bytes())?;
-        let derivation_path = DerivationPath::from_str(path)?;
-        let child = master.derive_path(&derivation_path)?;
-        let secret_key = child.private_key();
-        let public_key = secret_key.get_public_key();
-        let (x_only, _) = public_key.x_only_public_key();
-        let prefix = Prefix::from(self.network_type);
-        let address = Address::new(prefix, Version::PubKey, &x_only.serialize());
-
-        let extended = child.to_string(KeyPrefix::KPRV);
-        let private_key_hex = hex::encode(secret_key.secret_bytes());
-        let public_key_hex = hex::encode(public_key.serialize());
-        let x_only_public_key_hex = hex::encode(x_only.serialize());
-
-        Ok(DerivedKey {
-            extended_private_key: extended.to_string(),
-            private_key_hex,
-            public_key_hex,
-            x_only_public_key_hex,
-            address: address.to_string(),
-        })
+        self.runtime.block_on(self.inner.derive_private_key(mnemonic, path))
+    }
+
+    /// Scans the receive and change chains of `account_index` using the default gap limit of
+    /// [`DEFAULT_GAP_LIMIT`]. See [`Self::scan_accounts_with_gap_limit`] for details.
+    pub fn scan_accounts(
+        &self,
+        mnemonic: &str,
+        account_index: u32,
+    ) -> Result<AccountScanResult, WalletError> {
+        self.runtime.block_on(self.inner.scan_accounts(mnemonic, account_index))
+    }
+
+    /// Walks `m/44'/111111'/account_index'/0/i` (receive) and `.../1/i` (change), deriving each
+    /// address in turn and checking it for UTXOs via [`Self::get_utxos`]. A chain stops once
+    /// `gap_limit` consecutive addresses come back empty; the returned addresses are only those
+    /// that held funds (empty addresses are skipped), ready to feed into
+    /// [`Self::build_transaction`] or [`Self::sign_transaction`].
+    pub fn scan_accounts_with_gap_limit(
+        &self,
+        mnemonic: &str,
+        account_index: u32,
+        gap_limit: u32,
+    ) -> Result<AccountScanResult, WalletError> {
+        self.runtime
+            .block_on(self.inner.scan_accounts_with_gap_limit(mnemonic, account_index, gap_limit))
+    }
+
+    /// Exposes the async-native core this wallet blocks on, for callers that want to drive a
+    /// handful of calls on their own executor without fully migrating off the blocking API.
+    pub fn as_async(&self) -> &AsyncRustyKaspaWallet {
+        &self.inner
     }
 }
 
 impl Drop for RustyKaspaWallet {
     fn drop(&mut self) {
-        if let Some(client) = self.wrpc_client.take() {
-            let _ = self.runtime.block_on(async {
-                client.stop().await.ok();
-                client.disconnect().await.ok();
-            });
-        }
-        if let Some(client) = self.grpc_client.take() {
-            let _ = self.runtime.block_on(async {
-                client.join().await.ok();
-                client.disconnect().await.ok();
-            });
-        }
+        self.runtime.block_on(self.inner.shutdown());
+    }
+}
+
+struct CoinSelection {
+    utxos: Vec<RpcUtxosByAddressesEntry>,
+    change: u64,
+}
+
+/// Selects UTXOs covering `target` plus fees, preferring an exact Branch-and-Bound match over
+/// a largest-first fallback that adds a change output only when the surplus clears the dust
+/// threshold. `payload_len` is the byte length of whatever
+/// payload the transaction will carry, so it gets priced into the fee before inputs are chosen.
+fn select_coins(
+    spendable: &[RpcUtxosByAddressesEntry],
+    output_count: usize,
+    payload_len: usize,
+    target: u64,
+    fee_rate: u64,
+) -> Result<CoinSelection, WalletError> {
+    let mut candidates = spendable.to_vec();
+    candidates.sort_by(|a, b| b.utxo_entry.amount.cmp(&a.utxo_entry.amount));
+    let amounts: Vec<u64> = candidates.iter().map(|entry| entry.utxo_entry.amount).collect();
+
+    // Fee for the base transaction plus the requested outputs and payload, excluding inputs and
+    // any change.
+    let base_fee = (BASE_MASS + OUTPUT_MASS * output_count as u64 + payload_len as u64) * fee_rate;
+    let per_input_fee = INPUT_MASS * fee_rate;
+    // Cost of adding a change output; doubles as the BnB search's tolerance above target+fee.
+    let cost_of_change = OUTPUT_MASS * fee_rate;
+
+    let indices = branch_and_bound(&amounts, target, base_fee, per_input_fee, cost_of_change)
+        .or_else(|| largest_first(&amounts, target, base_fee, per_input_fee))
+        .ok_or(WalletError::InsufficientFunds)?;
+
+    let utxos: Vec<_> = indices.iter().map(|&i| candidates[i].clone()).collect();
+    let total: u64 = indices.iter().map(|&i| amounts[i]).sum();
+    let change = compute_change(utxos.len(), total, target, base_fee, per_input_fee, cost_of_change);
+    Ok(CoinSelection { utxos, change })
+}
+
+/// Computes the change left over from a selection of `num_inputs` totalling `total`, charging
+/// the fee for a change output only when the leftover still clears [`DUST_THRESHOLD`] once that
+/// output's own mass (`cost_of_change`) is accounted for; otherwise the leftover is absorbed into
+/// the fee instead of becoming a dust-or-worse change output.
+fn compute_change(
+    num_inputs: usize,
+    total: u64,
+    target: u64,
+    base_fee: u64,
+    per_input_fee: u64,
+    cost_of_change: u64,
+) -> u64 {
+    let fee = base_fee + per_input_fee * num_inputs as u64;
+    let change = total.saturating_sub(target + fee + cost_of_change);
+    if change > DUST_THRESHOLD {
+        change
+    } else {
+        0
     }
 }
 
-fn ensure_utxo_index(runtime: &Runtime, client: &KaspaRpcClient) -> Result<(), WalletError> {
-    let response = runtime
-        .block_on(client.get_server_info())
-        .map_err(WalletError::from)?;
-    enforce_utxo_index(response)
+/// Depth-first include/exclude search for a subset of `amounts` (sorted descending) whose total
+/// lands in `[target + fee, target + fee + cost_of_change]`, avoiding a change output.
+fn branch_and_bound(
+    amounts: &[u64],
+    target: u64,
+    base_fee: u64,
+    per_input_fee: u64,
+    cost_of_change: u64,
+) -> Option<Vec<usize>> {
+    let mut suffix_value = vec![0u64; amounts.len() + 1];
+    for i in (0..amounts.len()).rev() {
+        suffix_value[i] = suffix_value[i + 1] + amounts[i];
+    }
+
+    let mut selected = Vec::new();
+    let mut tries = 0usize;
+    let mut best = None;
+    bnb_step(
+        amounts,
+        &suffix_value,
+        0,
+        0,
+        &mut selected,
+        target,
+        base_fee,
+        per_input_fee,
+        cost_of_change,
+        &mut tries,
+        &mut best,
+    );
+    best
 }
 
-fn ensure_utxo_index_grpc(runtime: &Runtime, client: &GrpcClient) -> Result<(), WalletError> {
-    let response = runtime
-        .block_on(client.get_server_info())
-        .map_err(WalletError::from)?;
-    enforce_utxo_index(response)
+#[allow(clippy::too_many_arguments)]
+fn bnb_step(
+    amounts: &[u64],
+    suffix_value: &[u64],
+    index: usize,
+    current_value: u64,
+    selected: &mut Vec<usize>,
+    target: u64,
+    base_fee: u64,
+    per_input_fee: u64,
+    cost_of_change: u64,
+    tries: &mut usize,
+    best: &mut Option<Vec<usize>>,
+) {
+    if best.is_some() {
+        return;
+    }
+    *tries += 1;
+    if *tries > BNB_MAX_TRIES {
+        return;
+    }
+
+    let lower_bound = target + base_fee + per_input_fee * selected.len() as u64;
+    let upper_bound = lower_bound + cost_of_change;
+
+    if current_value > upper_bound {
+        return;
+    }
+    if current_value >= lower_bound {
+        *best = Some(selected.clone());
+        return;
+    }
+    if index >= amounts.len() || current_value + suffix_value[index] < lower_bound {
+        return;
+    }
+
+    selected.push(index);
+    bnb_step(
+        amounts,
+        suffix_value,
+        index + 1,
+        current_value + amounts[index],
+        selected,
+        target,
+        base_fee,
+        per_input_fee,
+        cost_of_change,
+        tries,
+        best,
+    );
+    selected.pop();
+
+    if best.is_some() {
+        return;
+    }
+
+    bnb_step(
+        amounts,
+        suffix_value,
+        index + 1,
+        current_value,
+        selected,
+        target,
+        base_fee,
+        per_input_fee,
+        cost_of_change,
+        tries,
+        best,
+    );
 }
 
-fn enforce_utxo_index(info: GetServerInfoResponse) -> Result<(), WalletError> {
-    if !info.has_utxo_index {
-        return Err(WalletError::MissingUtxoIndex);
+/// Accumulates the largest amounts first until the target plus fee is covered; used when
+/// Branch-and-Bound cannot find an exact-ish match. Returns the indices into `amounts` that were
+/// selected, in the order they were added (always a prefix of the descending-sorted slice).
+fn largest_first(amounts: &[u64], target: u64, base_fee: u64, per_input_fee: u64) -> Option<Vec<usize>> {
+    let mut total = 0u64;
+    for (i, amount) in amounts.iter().enumerate() {
+        total += amount;
+        let fee = base_fee + per_input_fee * (i as u64 + 1);
+        if total >= target + fee {
+            return Some((0..=i).collect());
+        }
     }
-    Ok(())
+    None
 }
 
 #[cfg(test)]
@@ -308,9 +464,7 @@ mod tests {
     fn derive_known_key() {
         let wallet = RustyKaspaWallet {
             runtime: Runtime::new().unwrap(),
-            network_type: NetworkType::Mainnet,
-            wrpc_client: None,
-            grpc_client: None,
+            inner: AsyncRustyKaspaWallet::offline(NetworkType::Mainnet),
         };
         let mnemonic =
             "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
@@ -334,4 +488,44 @@ mod tests {
             "kaspa:qz382fahc8pv0pn3xnu4d0etkds3764mc7zp8wrsrp3ztt58pu6vclrs67rdl"
         );
     }
+
+    #[test]
+    fn branch_and_bound_finds_exact_subset() {
+        let amounts = vec![2000u64, 1000, 500];
+        let indices = branch_and_bound(&amounts, 2000, 0, 0, 0).expect("bnb match");
+        let total: u64 = indices.iter().map(|&i| amounts[i]).sum();
+        assert_eq!(total, 2000);
+    }
+
+    #[test]
+    fn largest_first_falls_back_when_bnb_has_no_match() {
+        let amounts = vec![2000u64, 1000, 500];
+        assert!(branch_and_bound(&amounts, 1800, 10, 5, 2).is_none());
+        let indices = largest_first(&amounts, 1800, 10, 5).expect("largest-first match");
+        assert_eq!(indices, vec![0]);
+    }
+
+    #[test]
+    fn largest_first_returns_none_when_funds_are_insufficient() {
+        let amounts = vec![100u64, 50];
+        assert!(largest_first(&amounts, 10_000, 0, 0).is_none());
+    }
+
+    #[test]
+    fn compute_change_absorbs_dust_sized_leftovers_into_fee() {
+        // Leftover sits exactly at the dust threshold, so it's absorbed into the fee instead of
+        // becoming a change output.
+        let change = compute_change(1, 1_000 + DUST_THRESHOLD, 1_000, 0, 0, 0);
+        assert_eq!(change, 0);
+    }
+
+    #[test]
+    fn compute_change_includes_change_output_mass_in_the_fee() {
+        // Regression test: the fee basis must include `cost_of_change` whenever a change output
+        // is actually produced, or the transaction under-pays by one output's worth of mass.
+        let cost_of_change = 50;
+        let total = 1_000 + DUST_THRESHOLD + cost_of_change + 1;
+        let change = compute_change(1, total, 1_000, 0, 0, cost_of_change);
+        assert_eq!(change, total - 1_000 - cost_of_change);
+    }
 }
@@ -0,0 +1,172 @@
+//! Real-time UTXO and virtual-chain notifications, built over the streams
+//! [`crate::AsyncRustyKaspaWallet`] returns.
+//!
+//! Subscriptions are plain blocking receivers backed by a task spawned on the wallet's own
+//! [`tokio::runtime::Runtime`] that drains the async stream; this matches the rest of the crate's
+//! synchronous surface while letting callers react to incoming payments and chain re-orgs instead
+//! of polling `get_utxos`.
+
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+
+use futures::stream::StreamExt;
+use kaspa_addresses::Address;
+use kaspa_notify::listener::ListenerId;
+use kaspa_rpc_core::model::address::RpcUtxosByAddressesEntry;
+use kaspa_rpc_core::model::tx::RpcTransactionId;
+use kaspa_rpc_core::RpcHash;
+use kaspa_wrpc_client::KaspaRpcClient;
+use tokio::runtime::Handle;
+
+use crate::{RustyKaspaWallet, WalletError};
+
+/// A batch of UTXO changes for the watched address set.
+#[derive(Clone, Debug)]
+pub struct UtxoChanged {
+    pub added: Vec<RpcUtxosByAddressesEntry>,
+    pub removed: Vec<RpcUtxosByAddressesEntry>,
+}
+
+/// A live subscription to [`UtxoChanged`] events; unregisters itself and stops forwarding events
+/// when dropped, whether explicitly via [`RustyKaspaWallet::unwatch`] or implicitly by going out
+/// of scope.
+pub struct UtxoSubscription {
+    listener_id: ListenerId,
+    receiver: Receiver<UtxoChanged>,
+    client: Option<Arc<KaspaRpcClient>>,
+    handle: Handle,
+}
+
+impl UtxoSubscription {
+    /// Blocks until the next UTXO change arrives, or returns `None` once the subscription is
+    /// torn down.
+    pub fn recv(&self) -> Option<UtxoChanged> {
+        self.receiver.recv().ok()
+    }
+
+    /// Returns a pending UTXO change without blocking, if one is already queued.
+    pub fn try_recv(&self) -> Option<UtxoChanged> {
+        self.receiver.try_recv().ok()
+    }
+
+    pub(crate) fn listener_id(&self) -> ListenerId {
+        self.listener_id
+    }
+}
+
+impl Drop for UtxoSubscription {
+    /// `Drop` can't be `async`, so unregistering is a fire-and-forget task on the wallet's
+    /// runtime rather than a blocking call; a lingering listener otherwise keeps forwarding
+    /// events nobody is receiving until the whole wallet is dropped.
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            let listener_id = self.listener_id;
+            self.handle.spawn(async move {
+                client.unregister_listener(listener_id).await.ok();
+            });
+        }
+    }
+}
+
+/// The set of transaction IDs accepted into the DAG's selected chain as it advanced, and the
+/// chain block hashes that were removed by a reorg.
+#[derive(Clone, Debug)]
+pub struct VirtualChainChanged {
+    pub accepted_transaction_ids: Vec<RpcTransactionId>,
+    pub removed_chain_block_hashes: Vec<RpcHash>,
+}
+
+/// A live subscription to [`VirtualChainChanged`] events; see [`UtxoSubscription`] for its
+/// teardown-on-drop behavior.
+pub struct VirtualChainSubscription {
+    listener_id: ListenerId,
+    receiver: Receiver<VirtualChainChanged>,
+    client: Option<Arc<KaspaRpcClient>>,
+    handle: Handle,
+}
+
+impl VirtualChainSubscription {
+    pub fn recv(&self) -> Option<VirtualChainChanged> {
+        self.receiver.recv().ok()
+    }
+
+    pub fn try_recv(&self) -> Option<VirtualChainChanged> {
+        self.receiver.try_recv().ok()
+    }
+
+    pub(crate) fn listener_id(&self) -> ListenerId {
+        self.listener_id
+    }
+}
+
+impl Drop for VirtualChainSubscription {
+    /// See [`UtxoSubscription::drop`].
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            let listener_id = self.listener_id;
+            self.handle.spawn(async move {
+                client.unregister_listener(listener_id).await.ok();
+            });
+        }
+    }
+}
+
+impl RustyKaspaWallet {
+    /// Registers for UTXO-changed notifications on `addresses` and returns a subscription that
+    /// yields a [`UtxoChanged`] event every time the set of UTXOs owned by one of them changes.
+    /// Requires a wRPC connection; gRPC does not expose notification channels in this crate.
+    pub fn watch_addresses(&self, addresses: Vec<Address>) -> Result<UtxoSubscription, WalletError> {
+        let (listener_id, mut stream) = self.runtime.block_on(self.inner.watch_addresses(addresses))?;
+
+        let (event_sender, event_receiver) = channel();
+        self.runtime.spawn(async move {
+            while let Some(changed) = stream.next().await {
+                if event_sender.send(changed).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(UtxoSubscription {
+            listener_id,
+            receiver: event_receiver,
+            client: self.inner.wrpc_client(),
+            handle: self.runtime.handle().clone(),
+        })
+    }
+
+    /// Registers for virtual-chain-changed notifications and returns a subscription that yields
+    /// a [`VirtualChainChanged`] event every time the DAG's selected chain advances, surfacing
+    /// accepted transaction IDs and the hashes of chain blocks removed by a reorg instead of
+    /// requiring the caller to poll for confirmations. Transactions of removed blocks are not
+    /// listed by ID.
+    pub fn subscribe_virtual_chain(&self) -> Result<VirtualChainSubscription, WalletError> {
+        let (listener_id, mut stream) = self.runtime.block_on(self.inner.subscribe_virtual_chain())?;
+
+        let (event_sender, event_receiver) = channel();
+        self.runtime.spawn(async move {
+            while let Some(changed) = stream.next().await {
+                if event_sender.send(changed).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(VirtualChainSubscription {
+            listener_id,
+            receiver: event_receiver,
+            client: self.inner.wrpc_client(),
+            handle: self.runtime.handle().clone(),
+        })
+    }
+
+    /// Unregisters a previously created subscription, if the wallet is still connected.
+    pub fn unwatch(&self, subscription: UtxoSubscription) -> Result<(), WalletError> {
+        self.runtime.block_on(self.inner.unregister_listener(subscription.listener_id()))
+    }
+
+    /// Unregisters a previously created virtual-chain subscription.
+    pub fn unsubscribe_virtual_chain(&self, subscription: VirtualChainSubscription) -> Result<(), WalletError> {
+        self.runtime.block_on(self.inner.unregister_listener(subscription.listener_id()))
+    }
+}
@@ -0,0 +1,140 @@
+//! Transaction payload helpers, including an optional encrypted-memo mode addressed to a
+//! specific recipient.
+//!
+//! A memo payload is tagged with [`MEMO_TAG`] so a wallet can tell it apart from an ordinary
+//! application payload; everything after the tag is an AEAD ciphertext keyed by an ECDH shared
+//! secret between the sender's private key and the recipient's x-only public key, so only the
+//! two parties can read it while everyone else sees opaque bytes.
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use secp256k1::ecdh::SharedSecret;
+use secp256k1::{Parity, PublicKey, SecretKey, XOnlyPublicKey};
+
+use kaspa_rpc_core::model::tx::RpcTransaction;
+
+use crate::WalletError;
+
+/// Marks a payload as an encrypted memo rather than application-defined bytes.
+const MEMO_TAG: u8 = 0xE1;
+const MEMO_NONCE_LEN: usize = 24;
+
+/// Returns the raw payload attached to a fetched transaction.
+pub fn read_payload(transaction: &RpcTransaction) -> &[u8] {
+    &transaction.payload
+}
+
+/// Encrypts `memo` for `recipient_x_only_pubkey_hex`, deriving the AEAD key via ECDH from
+/// `sender_private_key_hex`. The result is ready to pass to
+/// [`crate::TransactionBuilder::with_payload`].
+pub fn encrypt_memo(
+    memo: &[u8],
+    sender_private_key_hex: &str,
+    recipient_x_only_pubkey_hex: &str,
+) -> Result<Vec<u8>, WalletError> {
+    let shared_secret = ecdh_shared_secret(sender_private_key_hex, recipient_x_only_pubkey_hex)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(shared_secret.as_ref()));
+
+    let mut nonce_bytes = [0u8; MEMO_NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = XNonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher.encrypt(nonce, memo).map_err(|_| WalletError::Decrypt)?;
+
+    let mut payload = Vec::with_capacity(1 + MEMO_NONCE_LEN + ciphertext.len());
+    payload.push(MEMO_TAG);
+    payload.extend_from_slice(&nonce_bytes);
+    payload.extend_from_slice(&ciphertext);
+    Ok(payload)
+}
+
+/// Attempts to decrypt `payload` as a memo addressed to `recipient_private_key_hex` from
+/// `sender_x_only_pubkey_hex`. Returns `Ok(None)` when the payload isn't tagged as a memo, or
+/// when it is but fails to authenticate (i.e. it was addressed to someone else) — only a
+/// malformed hex key is reported as an error.
+pub fn decrypt_memo(
+    payload: &[u8],
+    recipient_private_key_hex: &str,
+    sender_x_only_pubkey_hex: &str,
+) -> Result<Option<Vec<u8>>, WalletError> {
+    if payload.len() < 1 + MEMO_NONCE_LEN || payload[0] != MEMO_TAG {
+        return Ok(None);
+    }
+
+    let shared_secret = ecdh_shared_secret(recipient_private_key_hex, sender_x_only_pubkey_hex)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(shared_secret.as_ref()));
+
+    let (nonce_bytes, ciphertext) = payload[1..].split_at(MEMO_NONCE_LEN);
+    let nonce = XNonce::from_slice(nonce_bytes);
+    Ok(cipher.decrypt(nonce, ciphertext).ok())
+}
+
+/// Derives an ECDH shared secret between `private_key_hex` and `x_only_pubkey_hex`.
+///
+/// Each side only knows the *other* party's key as an x-only point (matching how
+/// [`crate::DerivedKey`] stores `x_only_public_key_hex`), so it has to pick an arbitrary parity
+/// to lift it to a full point before scalar multiplication — here we always lift to
+/// [`Parity::Even`]. Point negation only flips the y-coordinate, and scalar multiplication
+/// distributes over negation (`k * (-P) == -(k * P)`), so whichever parity the other party's
+/// true key actually has, the resulting shared point differs from the "real" `a*b*G` by at most
+/// a sign: both sides always land on a point with the same x-coordinate. Taking that raw
+/// x-coordinate as the key (via `new_with_hash_fn`, ignoring `y`) therefore yields identical key material on
+/// both sides; the default `SharedSecret::new` hashes the *compressed* point including the sign
+/// byte and would silently disagree whenever the two true keys have different y-parity.
+fn ecdh_shared_secret(private_key_hex: &str, x_only_pubkey_hex: &str) -> Result<SharedSecret, WalletError> {
+    let private_key_bytes = hex::decode(private_key_hex)?;
+    let secret_key = SecretKey::from_slice(&private_key_bytes).map_err(|_| WalletError::Decrypt)?;
+
+    let x_only_bytes = hex::decode(x_only_pubkey_hex)?;
+    let x_only = XOnlyPublicKey::from_slice(&x_only_bytes).map_err(|_| WalletError::Decrypt)?;
+    let public_key = PublicKey::from_x_only_public_key(x_only, Parity::Even);
+
+    Ok(SharedSecret::new_with_hash_fn(&public_key, &secret_key, |x, _y| {
+        let mut secret = [0u8; 32];
+        secret.copy_from_slice(x);
+        secret
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::Secp256k1;
+
+    fn keypair(byte: u8) -> (String, String) {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[byte; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let (x_only, _parity) = public_key.x_only_public_key();
+        (hex::encode(secret_key.secret_bytes()), hex::encode(x_only.serialize()))
+    }
+
+    #[test]
+    fn memo_round_trips_regardless_of_key_parity() {
+        // Sweeping several sender/recipient pairs exercises both possible y-parities on each
+        // side, which is what the x-only ECDH lift must agree on to decrypt correctly.
+        for sender_byte in [0x11u8, 0x22, 0x33, 0x44] {
+            for recipient_byte in [0x55u8, 0x66, 0x77, 0x88] {
+                let (sender_sk, sender_xonly) = keypair(sender_byte);
+                let (recipient_sk, recipient_xonly) = keypair(recipient_byte);
+
+                let memo = b"pay the invoice".to_vec();
+                let payload = encrypt_memo(&memo, &sender_sk, &recipient_xonly).expect("encrypt");
+                let decrypted = decrypt_memo(&payload, &recipient_sk, &sender_xonly).expect("decrypt");
+                assert_eq!(decrypted, Some(memo));
+            }
+        }
+    }
+
+    #[test]
+    fn memo_addressed_to_someone_else_fails_silently() {
+        let (sender_sk, sender_xonly) = keypair(0x11);
+        let (_recipient_sk, recipient_xonly) = keypair(0x22);
+        let (eavesdropper_sk, _) = keypair(0x33);
+
+        let payload = encrypt_memo(b"secret", &sender_sk, &recipient_xonly).expect("encrypt");
+        let decrypted = decrypt_memo(&payload, &eavesdropper_sk, &sender_xonly).expect("decrypt");
+        assert_eq!(decrypted, None);
+    }
+}